@@ -0,0 +1,302 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{Config, NonEmptyConfig};
+use anyhow::anyhow;
+use canvas_cli::{progress::Spinner, CanvasId};
+use fuzzy_matcher::FuzzyMatcher;
+use inquire::Select;
+use serde_derive::Deserialize;
+
+#[derive(clap::Parser, Debug)]
+/// Administer a Canvas account
+pub struct AdminCommand {
+    #[command(subcommand)]
+    action: AdminAction,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum AdminAction {
+    Report(ReportCommand),
+}
+
+fn parse_parameter(input: &str) -> Result<(String, String), String> {
+    match input.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => Err(String::from("Parameter must be of the form key=value")),
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+/// Run an account report and download the result
+pub struct ReportCommand {
+    /// Report type, such as provisioning_csv or grade_export_csv
+    report: Option<String>,
+
+    /// Canvas account ID or SIS account ID (sis_account_id:...)
+    #[clap(long, short)]
+    account: Option<CanvasId>,
+
+    /// Report parameter, such as users=true or enrollment_term_id=1
+    #[clap(long = "param", short, value_parser = parse_parameter)]
+    parameters: Vec<(String, String)>,
+
+    /// Output directory
+    #[clap(long, short)]
+    directory: Option<PathBuf>,
+
+    /// Seconds to wait between checking on the report
+    #[clap(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+
+    /// Minutes to wait for the report to complete before giving up, at most a day
+    #[clap(long, default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..=1440))]
+    timeout: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct AccountResponse {
+    id: u32,
+    name: String,
+}
+
+impl Display for AccountResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.id)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct AvailableReportResponse {
+    report: String,
+    title: String,
+}
+
+impl Display for AvailableReportResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.title, self.report)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct AttachmentResponse {
+    url: String,
+    filename: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReportResponse {
+    id: u32,
+    status: String,
+    progress: Option<u32>,
+    attachment: Option<AttachmentResponse>,
+}
+
+impl AdminCommand {
+    pub async fn action(&self, cfg: &Config) -> Result<(), anyhow::Error> {
+        match &self.action {
+            AdminAction::Report(command) => command.action(cfg).await,
+        }
+    }
+}
+
+impl ReportCommand {
+    pub async fn action(&self, cfg: &Config) -> Result<(), anyhow::Error> {
+        let NonEmptyConfig {
+            url: base_url,
+            access_token,
+        } = cfg.ensure_non_empty()?;
+
+        let client = reqwest::Client::builder()
+            .default_headers(
+                std::iter::once((
+                    reqwest::header::AUTHORIZATION,
+                    reqwest::header::HeaderValue::from_str(&format!("Bearer {}", access_token))
+                        .unwrap(),
+                ))
+                .collect(),
+            )
+            .build()
+            .unwrap();
+
+        let account_id = if let Some(account_id) = &self.account {
+            account_id.path()
+        } else {
+            let mut accounts = client
+                .get(format!("{}/api/v1/accounts?per_page=1000", base_url))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Vec<AccountResponse>>()
+                .await?;
+            log::info!("Made REST request to get administered accounts");
+
+            match accounts.len() {
+                0 => Err(anyhow!("You do not administer any accounts"))?,
+                1 => {
+                    let account = accounts.remove(0);
+                    println!("✓ Found {account}");
+                    account.id.to_string()
+                }
+                _ => {
+                    println!("✓ Queried account information");
+                    Select::new("Account?", accounts).prompt()?.id.to_string()
+                }
+            }
+        };
+
+        log::info!("Selected account {}", account_id);
+
+        let report = if let Some(report) = &self.report {
+            report.clone()
+        } else {
+            let reports = client
+                .get(format!(
+                    "{}/api/v1/accounts/{}/reports",
+                    base_url, account_id
+                ))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Vec<AvailableReportResponse>>()
+                .await?;
+            log::info!("Made REST request to get available reports");
+            println!("✓ Queried available reports");
+
+            let matcher = fuzzy_matcher::skim::SkimMatcherV2::default();
+            Select::new("Report?", reports)
+                .with_filter(&|input, _, string_value, _| {
+                    matcher.fuzzy_match(string_value, input).is_some()
+                })
+                .prompt()?
+                .report
+        };
+
+        let params: Vec<(String, String)> = self
+            .parameters
+            .iter()
+            .map(|(k, v)| (format!("parameters[{}]", k), v.clone()))
+            .collect();
+
+        let spinner = Spinner::new(format!("Starting report {}", report));
+
+        let mut report_response = client
+            .post(format!(
+                "{}/api/v1/accounts/{}/reports/{}",
+                base_url, account_id, report
+            ))
+            .form(&params)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<ReportResponse>()
+            .await?;
+        log::info!("Started report {}", report_response.id);
+
+        let deadline =
+            tokio::time::Instant::now() + std::time::Duration::from_secs(self.timeout * 60);
+
+        let attachment = loop {
+            match report_response.status.as_str() {
+                "complete" => {
+                    break report_response
+                        .attachment
+                        .ok_or_else(|| anyhow!("Report {} completed without a file", report))?
+                }
+                "error" | "aborted" | "deleted" => {
+                    spinner.fail(format!("Report {} {}", report, report_response.status));
+                    return Err(anyhow!(
+                        "Report {} did not complete, check the account reports page for details",
+                        report
+                    ));
+                }
+                status => spinner.set_message(format!(
+                    "Generating report {}: {} ({}%)",
+                    report,
+                    status,
+                    report_response.progress.unwrap_or(0)
+                )),
+            }
+
+            if tokio::time::Instant::now() >= deadline {
+                spinner.fail(format!(
+                    "Report {} still {} after {} minutes",
+                    report, report_response.status, self.timeout
+                ));
+                return Err(anyhow!(
+                    "Timed out waiting for report {}, it may still complete on the account reports page",
+                    report
+                ));
+            }
+
+            tokio::time::sleep(std::time::Duration::from_secs(self.interval)).await;
+
+            report_response = client
+                .get(format!(
+                    "{}/api/v1/accounts/{}/reports/{}/{}",
+                    base_url, account_id, report, report_response.id
+                ))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<ReportResponse>()
+                .await?;
+            log::info!("Made REST request to get report status");
+        };
+
+        // the filename comes from the server, so never let it point outside the output directory
+        let filename = match Path::new(&attachment.filename).file_name() {
+            Some(filename) => filename,
+            None => {
+                spinner.fail(format!("Report {} has no usable filename", report));
+                return Err(anyhow!(
+                    "Report {} has an invalid filename: {}",
+                    report,
+                    attachment.filename
+                ));
+            }
+        };
+
+        spinner.set_message(format!("Downloading report {}", filename.to_string_lossy()));
+
+        let path = if let Some(directory) = &self.directory {
+            fs::create_dir_all(directory)?;
+            directory.join(filename)
+        } else {
+            PathBuf::from(filename)
+        };
+
+        let content = client
+            .get(&attachment.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        let mut file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                spinner.fail(format!("Report {} already exists", path.display()));
+                return Err(anyhow!(
+                    "{} already exists, move it or use another --directory",
+                    path.display()
+                ));
+            }
+            Err(error) => Err(error)?,
+        };
+        file.write_all(&content)?;
+
+        spinner.finish(format!("Downloaded report {}", path.display()));
+
+        Ok(())
+    }
+}
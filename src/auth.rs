@@ -1,4 +1,4 @@
-use indicatif::ProgressStyle;
+use canvas_cli::progress::Spinner;
 use inquire::{Password, PasswordDisplayMode, Text};
 use serde_derive::Deserialize;
 
@@ -70,16 +70,7 @@ impl AuthCommand {
             .build()
             .unwrap();
 
-        let spinner = indicatif::ProgressBar::new_spinner();
-        spinner.set_message("Test query with authentication");
-
-        let spinner_clone = spinner.clone();
-        let spinner_task = tokio::spawn(async move {
-            loop {
-                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                spinner_clone.inc(1);
-            }
-        });
+        let spinner = Spinner::new("Test query with authentication");
 
         let self_query = client
             .get(format!("{}/api/v1/users/self", url))
@@ -87,10 +78,8 @@ impl AuthCommand {
             .await?
             .json::<SelfResponse>()
             .await?;
-        spinner_task.abort();
 
-        spinner.set_style(ProgressStyle::with_template("✓ {wide_msg}").unwrap());
-        spinner.finish_with_message("Test query successful");
+        spinner.finish("Test query successful");
         println!("Authenticated as: ");
         match self_query.pronouns {
             Some(p) => println!("  {} ({})", self_query.name, p),
@@ -1,12 +1,19 @@
-use std::{fmt::Display, fs, io::Cursor, path::PathBuf};
-
-use crate::{Config, NonEmptyConfig};
-use canvas_cli::{Course, DateTime};
+use std::{
+    fmt::Display,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use crate::{Config, DownloadNaming, NonEmptyConfig};
+use anyhow::anyhow;
+use canvas_cli::{progress::Spinner, CanvasId, Course, DateTime};
 use fuzzy_matcher::FuzzyMatcher;
 use human_bytes::human_bytes;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use indicatif::MultiProgress;
 use inquire::MultiSelect;
 use regex::Regex;
+use reqwest::Client;
 use serde_derive::Deserialize;
 
 #[derive(Debug)]
@@ -33,12 +40,28 @@ struct FileResponse {
     updated_at: DateTime,
 }
 
+#[derive(Deserialize, Debug)]
+struct SyllabusResponse {
+    syllabus_body: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PageResponse {
+    url: String,
+    title: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct PageBodyResponse {
+    body: Option<String>,
+}
+
 #[derive(clap::Parser, Debug)]
-/// Download files from a course
+/// Download files, the syllabus or pages from a course
 pub struct DownloadCommand {
-    /// Canvas course ID
+    /// Canvas course ID or SIS course ID (sis_course_id:...)
     #[clap(long, short)]
-    course: Option<u32>,
+    course: Option<CanvasId>,
 
     /// Canvas URL to parse
     #[clap(long, short)]
@@ -48,31 +71,47 @@ pub struct DownloadCommand {
     #[clap(value_parser, num_args = 1.., value_delimiter = ' ')]
     files: Option<Vec<u32>>,
 
-    /// Output directory
+    /// Output directory, defaults to a directory for the course inside download_directory if
+    /// that is configured, otherwise the current directory
     #[clap(long, short)]
     directory: Option<PathBuf>,
+
+    /// Download the syllabus as syllabus.html
+    #[clap(long)]
+    syllabus: bool,
+
+    /// Download every page as <page>.html
+    #[clap(long)]
+    pages: bool,
+
+    /// Access a public course without logging in, which requires the course URL or ID
+    #[clap(long)]
+    public: bool,
 }
 
 impl DownloadCommand {
     pub async fn action(&self, cfg: &Config) -> Result<(), anyhow::Error> {
-        let NonEmptyConfig {
-            url: mut base_url,
-            access_token,
-        } = cfg.ensure_non_empty()?;
-
-        let client = reqwest::Client::builder()
-            .default_headers(
-                std::iter::once((
-                    reqwest::header::AUTHORIZATION,
-                    reqwest::header::HeaderValue::from_str(&format!("Bearer {}", access_token))
-                        .unwrap(),
-                ))
-                .collect(),
-            )
-            .build()
-            .unwrap();
-
-        let mut course_id = self.course;
+        let (mut base_url, client) = if self.public {
+            (cfg.url.clone().unwrap_or_default(), reqwest::Client::new())
+        } else {
+            let NonEmptyConfig { url, access_token } = cfg.ensure_non_empty()?;
+
+            let client = reqwest::Client::builder()
+                .default_headers(
+                    std::iter::once((
+                        reqwest::header::AUTHORIZATION,
+                        reqwest::header::HeaderValue::from_str(&format!("Bearer {}", access_token))
+                            .unwrap(),
+                    ))
+                    .collect(),
+                )
+                .build()
+                .unwrap();
+
+            (url, client)
+        };
+
+        let mut course_id = self.course.clone();
         let canvas_file_url = if let Ok(env_canvas_url) = std::env::var("CANVAS_URL") {
             Some(env_canvas_url)
         } else {
@@ -84,20 +123,58 @@ impl DownloadCommand {
 
             let captures = regex.captures(&canvas_assignment_url).unwrap();
             base_url = captures.get(1).unwrap().as_str().to_string();
-            course_id = Some(captures.get(2).unwrap().as_str().parse::<u32>().unwrap());
+            course_id = Some(CanvasId::Id(
+                captures.get(2).unwrap().as_str().parse::<u32>().unwrap(),
+            ));
         }
 
         if let Ok(env_canvas_course_id) = std::env::var("CANVAS_COURSE_ID") {
-            course_id = Some(env_canvas_course_id.parse::<u32>().unwrap())
+            course_id = Some(env_canvas_course_id.parse::<CanvasId>().unwrap())
         }
 
         let base_url = base_url;
         let course_id = course_id;
 
-        let course = Course::fetch(course_id, &base_url, &client).await?;
+        let course = if self.public {
+            match (base_url.is_empty(), course_id) {
+                (false, Some(course_id)) => {
+                    Course::fetch_public(&course_id, &base_url, &client).await?
+                }
+                _ => Err(anyhow!(
+                    "Public courses must be given with --url, or --course with a configured Canvas URL"
+                ))?,
+            }
+        } else {
+            Course::fetch_with_colors(course_id.as_ref(), &base_url, &client).await?
+        };
 
         log::info!("Selected course {}", course.id);
 
+        let directory = match (&self.directory, &cfg.download_directory) {
+            (Some(directory), _) => Some(directory.clone()),
+            (None, Some(download_directory)) => {
+                let name = match cfg.download_naming {
+                    DownloadNaming::CourseCode => &course.course_code,
+                    DownloadNaming::Nickname => &course.name,
+                };
+                Some(download_root(download_directory)?.join(course_directory(name, course.id)))
+            }
+            (None, None) => None,
+        };
+
+        if self.syllabus {
+            download_syllabus(&base_url, &client, &course, directory.as_ref()).await?;
+        }
+
+        if self.pages {
+            download_pages(&base_url, &client, &course, directory.as_ref()).await?;
+        }
+
+        // only fall back to picking files when nothing else was asked for
+        if (self.syllabus || self.pages) && self.files.is_none() {
+            return Ok(());
+        }
+
         let file_request = client
             .get(format!(
                 "{}/api/v1/courses/{}/files?per_page=1000",
@@ -124,7 +201,7 @@ impl DownloadCommand {
             })
             .collect();
 
-        if files.len() == 0 {
+        if files.is_empty() {
             println!("No files available");
             return Ok(());
         }
@@ -134,7 +211,7 @@ impl DownloadCommand {
             files.retain(|file| file_ids.contains(&file.id));
             files
         } else {
-            files.sort_by(|a, b| a.updated_at.cmp(&b.updated_at));
+            files.sort_by_key(|file| file.updated_at);
             let matcher = fuzzy_matcher::skim::SkimMatcherV2::default();
             let files = MultiSelect::new("Files?", files)
                 .with_filter(&|input, _, string_value, _| {
@@ -144,12 +221,12 @@ impl DownloadCommand {
             files
         };
 
-        if files.len() == 0 {
+        if files.is_empty() {
             println!("No files selected");
             return Ok(());
         }
 
-        if let Some(directory) = &self.directory {
+        if let Some(directory) = &directory {
             fs::create_dir_all(directory)?;
             println!(
                 "✓ Will download files into {}",
@@ -160,7 +237,7 @@ impl DownloadCommand {
         let multi_progress = MultiProgress::new();
         let future_files = files
             .iter()
-            .map(|file| upload_file(&file, self.directory.as_ref(), &multi_progress));
+            .map(|file| upload_file(file, directory.as_ref(), &multi_progress));
         futures::future::join_all(future_files).await;
 
         println!("✓ Successfully downloaded files 🎉");
@@ -174,31 +251,193 @@ async fn upload_file(
     directory: Option<&PathBuf>,
     multi_progress: &MultiProgress,
 ) -> Result<(), anyhow::Error> {
-    let spinner = multi_progress.add(ProgressBar::new_spinner());
-    spinner.set_message(format!("Downloading file {}", file));
-
-    let spinner_clone = spinner.clone();
-    let spinner_task = tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-            spinner_clone.inc(1);
-        }
-    });
+    let spinner = Spinner::new_in(multi_progress, format!("Downloading file {}", file));
 
-    let path = if let Some(directory) = directory {
-        directory.join(&file.filename)
-    } else {
-        PathBuf::from(&file.filename)
-    };
+    let path = output_path(directory, &file.filename);
 
     let response = reqwest::get(&file.url).await?;
     let mut fsfile = std::fs::File::create(path)?;
     let mut content = Cursor::new(response.bytes().await?);
     std::io::copy(&mut content, &mut fsfile)?;
 
-    spinner_task.abort();
-    spinner.set_style(ProgressStyle::with_template("✓ {wide_msg}").unwrap());
-    spinner.finish_with_message(format!("Downloaded file {}", file));
+    spinner.finish(format!("Downloaded file {}", file));
+
+    Ok(())
+}
+
+/// Name of the directory for a course, without characters that are not allowed in paths
+fn course_directory(name: &str, course_id: u32) -> String {
+    let directory: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c => c,
+        })
+        .collect();
+
+    // these would not be a directory of their own
+    match directory.as_str() {
+        "" | "." | ".." => course_id.to_string(),
+        _ => directory,
+    }
+}
+
+/// Expand a leading `~` in the configured download_directory, which is not relative to wherever
+/// the command happens to run
+fn download_root(download_directory: &Path) -> Result<PathBuf, anyhow::Error> {
+    let directory = match download_directory.strip_prefix("~") {
+        Ok(rest) => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(rest))
+            .ok_or_else(|| anyhow!("Cannot expand ~ in download_directory, HOME is not set"))?,
+        Err(_) => download_directory.to_path_buf(),
+    };
+
+    if directory.is_relative() {
+        Err(anyhow!(
+            "download_directory must be an absolute path, got {}",
+            download_directory.display()
+        ))?;
+    }
+
+    Ok(directory)
+}
+
+/// Create the output directory only once there is something to write into it
+fn create_directory(directory: Option<&PathBuf>) -> Result<(), std::io::Error> {
+    match directory {
+        Some(directory) => fs::create_dir_all(directory),
+        None => Ok(()),
+    }
+}
+
+fn output_path(directory: Option<&PathBuf>, filename: &str) -> PathBuf {
+    if let Some(directory) = directory {
+        directory.join(filename)
+    } else {
+        PathBuf::from(filename)
+    }
+}
+
+async fn download_syllabus(
+    url: &str,
+    client: &Client,
+    course: &Course,
+    directory: Option<&PathBuf>,
+) -> Result<(), anyhow::Error> {
+    let spinner = Spinner::new("Downloading syllabus");
+
+    let syllabus = client
+        .get(format!(
+            "{}/api/v1/courses/{}?include[]=syllabus_body",
+            url, course.id
+        ))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<SyllabusResponse>()
+        .await?;
+    log::info!("Made REST request to get syllabus");
+
+    match syllabus.syllabus_body {
+        Some(body) if !body.is_empty() => {
+            create_directory(directory)?;
+            fs::write(output_path(directory, "syllabus.html"), body)?;
+            spinner.finish("Downloaded syllabus");
+        }
+        _ => spinner.finish("No syllabus available"),
+    }
 
     Ok(())
 }
+
+async fn download_pages(
+    url: &str,
+    client: &Client,
+    course: &Course,
+    directory: Option<&PathBuf>,
+) -> Result<(), anyhow::Error> {
+    let spinner = Spinner::new("Downloading pages");
+
+    let pages = client
+        .get(format!(
+            "{}/api/v1/courses/{}/pages?per_page=1000",
+            url, course.id
+        ))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<PageResponse>>()
+        .await?;
+    log::info!("Made REST request to get pages");
+
+    if !pages.is_empty() {
+        create_directory(directory)?;
+    }
+
+    for page in pages.iter() {
+        spinner.set_message(format!("Downloading page {}", page.title));
+
+        let body = client
+            .get(format!(
+                "{}/api/v1/courses/{}/pages/{}",
+                url, course.id, page.url
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<PageBodyResponse>()
+            .await?
+            .body
+            .unwrap_or_default();
+
+        fs::write(output_path(directory, &format!("{}.html", page.url)), body)?;
+    }
+
+    spinner.finish(format!("Downloaded {} pages", pages.len()));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn course_directory_replaces_path_characters() {
+        assert_eq!(course_directory(" CS 1/2: Intro? ", 7), "CS 1-2- Intro-");
+        assert_eq!(course_directory("a\\b*c\"d<e>f|g", 7), "a-b-c-d-e-f-g");
+    }
+
+    #[test]
+    fn course_directory_falls_back_to_course_id() {
+        assert_eq!(course_directory("", 42), "42");
+        assert_eq!(course_directory("  ", 42), "42");
+        assert_eq!(course_directory(".", 42), "42");
+        assert_eq!(course_directory("..", 42), "42");
+    }
+
+    #[test]
+    fn download_root_expands_home() {
+        std::env::set_var("HOME", "/home/student");
+        assert_eq!(
+            download_root(Path::new("~/canvas")).unwrap(),
+            PathBuf::from("/home/student/canvas")
+        );
+        assert_eq!(
+            download_root(Path::new("~")).unwrap(),
+            PathBuf::from("/home/student")
+        );
+        assert_eq!(
+            download_root(Path::new("/srv/canvas")).unwrap(),
+            PathBuf::from("/srv/canvas")
+        );
+    }
+
+    #[test]
+    fn download_root_rejects_relative_paths() {
+        assert!(download_root(Path::new("canvas")).is_err());
+        assert!(download_root(Path::new("./canvas")).is_err());
+        assert!(download_root(Path::new("~user/canvas")).is_err());
+    }
+}
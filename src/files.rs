@@ -0,0 +1,250 @@
+use std::path::{Path, PathBuf};
+
+use crate::{Config, NonEmptyConfig};
+use anyhow::anyhow;
+use canvas_cli::{
+    progress::Spinner,
+    upload::{self, UploadResponse},
+    CanvasId,
+};
+use colored::Colorize;
+use futures::StreamExt;
+use indicatif::MultiProgress;
+use reqwest::Client;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(clap::Parser, Debug)]
+/// Manage course files
+pub struct FilesCommand {
+    #[command(subcommand)]
+    action: FilesAction,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum FilesAction {
+    Distribute(DistributeCommand),
+}
+
+#[derive(clap::Parser, Debug)]
+/// Upload files to course folders or modules from a CSV manifest
+///
+/// The manifest needs a header row with the columns file, course, folder and module.
+/// course is a Canvas course ID or SIS course ID (sis_course_id:...).
+/// folder is a path inside the course files and module is a module ID, both may be left empty.
+/// Relative file paths are resolved against the directory containing the manifest.
+pub struct DistributeCommand {
+    /// CSV manifest
+    manifest: PathBuf,
+
+    /// Maximum number of concurrent uploads
+    #[clap(long, short, default_value_t = 4)]
+    jobs: usize,
+
+    /// Write a CSV report of every manifest row to this path
+    #[clap(long, short)]
+    report: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ManifestRow {
+    file: PathBuf,
+    course: CanvasId,
+    #[serde(default)]
+    folder: Option<String>,
+    #[serde(default)]
+    module: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+struct ReportRow {
+    file: String,
+    course: String,
+    folder: Option<String>,
+    module: Option<u32>,
+    status: &'static str,
+    file_id: Option<u32>,
+    error: Option<String>,
+}
+
+impl FilesCommand {
+    pub async fn action(&self, cfg: &Config) -> Result<(), anyhow::Error> {
+        match &self.action {
+            FilesAction::Distribute(command) => command.action(cfg).await,
+        }
+    }
+}
+
+impl DistributeCommand {
+    pub async fn action(&self, cfg: &Config) -> Result<(), anyhow::Error> {
+        let NonEmptyConfig {
+            url: base_url,
+            access_token,
+        } = cfg.ensure_non_empty()?;
+
+        if self.jobs == 0 {
+            Err(anyhow!("Must allow at least one concurrent upload"))?;
+        }
+
+        let manifest_directory = self
+            .manifest
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut rows = Vec::new();
+        for (index, row) in csv::Reader::from_path(&self.manifest)?
+            .deserialize::<ManifestRow>()
+            .enumerate()
+        {
+            // header is line 1
+            let mut row = row.map_err(|error| anyhow!("Manifest line {}: {}", index + 2, error))?;
+            if row.file.is_relative() {
+                row.file = manifest_directory.join(&row.file);
+            }
+            rows.push(row);
+        }
+
+        if rows.is_empty() {
+            Err(anyhow!("Manifest does not contain any rows"))?;
+        }
+
+        println!("✓ Read {} rows from manifest", rows.len());
+
+        // verify all files exist first before doing anything which needs a network connections
+        for row in rows.iter() {
+            match std::fs::metadata(&row.file) {
+                Ok(_) => Ok(()),
+                Err(error) => Err(anyhow!("{}: {}", error, row.file.display())),
+            }?;
+
+            log::info!("Verified file exists: {}", row.file.display());
+        }
+
+        println!("✓ Verified all files exist");
+
+        let client = reqwest::Client::builder()
+            .default_headers(
+                std::iter::once((
+                    reqwest::header::AUTHORIZATION,
+                    reqwest::header::HeaderValue::from_str(&format!("Bearer {}", access_token))
+                        .unwrap(),
+                ))
+                .collect(),
+            )
+            .build()
+            .unwrap();
+
+        let multi_progress = MultiProgress::new();
+        let report: Vec<ReportRow> = futures::stream::iter(rows.iter())
+            .map(|row| distribute_file(&base_url, &client, row, &multi_progress))
+            .buffered(self.jobs)
+            .collect()
+            .await;
+
+        let failed = report.iter().filter(|row| row.error.is_some()).count();
+
+        if let Some(report_path) = &self.report {
+            let mut writer = csv::Writer::from_path(report_path)?;
+            for row in report.iter() {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+            println!("✓ Wrote report to {}", report_path.display());
+        }
+
+        if failed > 0 {
+            for row in report.iter() {
+                if let Some(error) = &row.error {
+                    println!("{} {}: {}", "✗".red(), row.file, error);
+                }
+            }
+            Err(anyhow!("{} of {} uploads failed", failed, report.len()))?;
+        }
+
+        println!("✓ Successfully distributed {} files 🎉", report.len());
+
+        Ok(())
+    }
+}
+
+async fn distribute_file(
+    url: &str,
+    client: &Client,
+    row: &ManifestRow,
+    multi_progress: &MultiProgress,
+) -> ReportRow {
+    let filepath = row.file.display().to_string();
+
+    let spinner = Spinner::new_in(multi_progress, format!("Uploading file {}", filepath));
+
+    let (status, file_id, error) = match upload_file(url, client, row, &spinner).await {
+        Ok(upload_response) => {
+            spinner.finish(format!(
+                "Uploaded file {} as {}",
+                filepath,
+                upload_response
+                    .display_name
+                    .unwrap_or_else(|| upload_response.id.to_string())
+            ));
+            ("uploaded", Some(upload_response.id), None)
+        }
+        Err(error) => {
+            spinner.fail(format!("Failed to upload file {}", filepath));
+            ("failed", None, Some(error.to_string()))
+        }
+    };
+
+    ReportRow {
+        file: filepath,
+        course: row.course.to_string(),
+        folder: row.folder.clone(),
+        module: row.module,
+        status,
+        file_id,
+        error,
+    }
+}
+
+async fn upload_file(
+    url: &str,
+    client: &Client,
+    row: &ManifestRow,
+    spinner: &Spinner,
+) -> Result<UploadResponse, anyhow::Error> {
+    let filepath = row.file.display();
+
+    let params: Vec<(&str, &str)> = match &row.folder {
+        Some(folder) => vec![("parent_folder_path", folder.as_str())],
+        None => vec![],
+    };
+
+    let upload_response = upload::upload_file(
+        client,
+        &format!("{}/api/v1/courses/{}/files", url, row.course.path()),
+        &params,
+        &row.file,
+        spinner,
+    )
+    .await?;
+
+    if let Some(module) = row.module {
+        spinner.set_message(format!("Uploading {}: adding file to module", filepath));
+
+        client
+            .post(format!(
+                "{}/api/v1/courses/{}/modules/{}/items",
+                url,
+                row.course.path(),
+                module
+            ))
+            .form(&[
+                ("module_item[type]", "File".to_string()),
+                ("module_item[content_id]", upload_response.id.to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+    }
+
+    Ok(upload_response)
+}
@@ -0,0 +1,664 @@
+use std::{collections::HashMap, fmt::Display, path::PathBuf};
+
+use crate::{Config, NonEmptyConfig};
+use canvas_cli::{fetch_all_pages, next_page, progress::Spinner, CanvasId, Course, DateTime};
+use colored::Colorize;
+use futures::StreamExt;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[derive(clap::Parser, Debug)]
+/// Inspect grades
+pub struct GradesCommand {
+    #[command(subcommand)]
+    action: GradesAction,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum GradesAction {
+    History(HistoryCommand),
+    ExportMetadata(ExportMetadataCommand),
+}
+
+#[derive(clap::Parser, Debug)]
+/// Show when each grade was posted or changed
+pub struct HistoryCommand {
+    /// Canvas course ID or SIS course ID (sis_course_id:...)
+    #[clap(long, short)]
+    course: Option<CanvasId>,
+
+    /// Canvas user ID or SIS user ID (sis_user_id:...) of the student, defaults to yourself
+    #[clap(long, short)]
+    student: Option<CanvasId>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    Json,
+    Csv,
+}
+
+#[derive(clap::Parser, Debug)]
+/// Export timestamps, attempts, late status and file checksums of every submission to an assignment
+pub struct ExportMetadataCommand {
+    /// Canvas course ID or SIS course ID (sis_course_id:...)
+    #[clap(long, short)]
+    course: Option<CanvasId>,
+
+    /// Canvas assignment ID
+    #[clap(long, short)]
+    assignment: u32,
+
+    /// Output format
+    #[clap(long, short, value_enum, default_value_t = ExportFormat::Json)]
+    format: ExportFormat,
+
+    /// Output file, defaults to submissions-<assignment>.<format>
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+
+    /// Skip downloading submitted files to compute their SHA-256 checksums
+    #[clap(long)]
+    no_checksums: bool,
+}
+
+#[derive(Debug)]
+enum EventKind {
+    Graded {
+        grade: Option<String>,
+    },
+    Posted {
+        delay: Option<chrono::Duration>,
+    },
+    Changed {
+        before: Option<String>,
+        after: Option<String>,
+    },
+}
+
+#[derive(Debug)]
+struct Event {
+    at: DateTime,
+    assignment: String,
+    kind: EventKind,
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}  {}  ",
+            self.at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+                .dimmed(),
+            self.assignment
+        )?;
+        match &self.kind {
+            EventKind::Graded { grade } => write!(
+                f,
+                "graded {}",
+                grade.as_deref().unwrap_or("without a grade").green()
+            ),
+            EventKind::Posted { delay: Some(delay) } => write!(
+                f,
+                "{}",
+                format!("posted {} after grading", format_duration(*delay)).yellow()
+            ),
+            EventKind::Posted { delay: None } => write!(f, "posted"),
+            EventKind::Changed { before, after } => write!(
+                f,
+                "{} {} → {}",
+                "regraded".magenta(),
+                before.as_deref().unwrap_or("-"),
+                after.as_deref().unwrap_or("-")
+            ),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct SelfResponse {
+    id: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct SubmissionAssignmentResponse {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SubmissionResponse {
+    assignment_id: u32,
+    assignment: SubmissionAssignmentResponse,
+    grade: Option<String>,
+    graded_at: Option<DateTime>,
+    posted_at: Option<DateTime>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GradeChangeLinksResponse {
+    /// Missing for changes to the overall course grade
+    assignment: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GradeChangeEventResponse {
+    created_at: DateTime,
+    grade_before: Option<String>,
+    grade_after: Option<String>,
+    links: GradeChangeLinksResponse,
+}
+
+#[derive(Deserialize, Debug)]
+struct GradeChangeResponse {
+    events: Vec<GradeChangeEventResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SubmissionUserResponse {
+    name: String,
+    sis_user_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AttachmentResponse {
+    id: u32,
+    display_name: String,
+    size: u64,
+    url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SubmissionAttemptResponse {
+    attempt: Option<u32>,
+    submitted_at: Option<DateTime>,
+    #[serde(default)]
+    late: bool,
+    #[serde(default)]
+    seconds_late: u64,
+    #[serde(default)]
+    attachments: Vec<AttachmentResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AssignmentSubmissionResponse {
+    user_id: u32,
+    user: Option<SubmissionUserResponse>,
+    workflow_state: String,
+    attempt: Option<u32>,
+    grade: Option<String>,
+    graded_at: Option<DateTime>,
+    #[serde(default)]
+    late: bool,
+    #[serde(default)]
+    missing: bool,
+    #[serde(default)]
+    submission_history: Vec<SubmissionAttemptResponse>,
+}
+
+#[derive(Serialize, Debug)]
+struct FileRecord {
+    id: u32,
+    name: String,
+    size: u64,
+    sha256: Option<String>,
+    checksum_error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct AttemptRecord {
+    attempt: Option<u32>,
+    submitted_at: Option<DateTime>,
+    late: bool,
+    seconds_late: u64,
+    files: Vec<FileRecord>,
+}
+
+#[derive(Serialize, Debug)]
+struct SubmissionRecord {
+    user_id: u32,
+    user_name: Option<String>,
+    sis_user_id: Option<String>,
+    workflow_state: String,
+    attempts: Option<u32>,
+    grade: Option<String>,
+    graded_at: Option<DateTime>,
+    late: bool,
+    missing: bool,
+    history: Vec<AttemptRecord>,
+}
+
+/// One row per submitted file, or per attempt without files, since CSV cannot nest
+#[derive(Serialize, Debug)]
+struct SubmissionRow<'a> {
+    user_id: u32,
+    user_name: Option<&'a str>,
+    sis_user_id: Option<&'a str>,
+    workflow_state: &'a str,
+    attempts: Option<u32>,
+    grade: Option<&'a str>,
+    graded_at: Option<DateTime>,
+    late: bool,
+    missing: bool,
+    attempt: Option<u32>,
+    submitted_at: Option<DateTime>,
+    attempt_late: bool,
+    seconds_late: u64,
+    file_id: Option<u32>,
+    file_name: Option<&'a str>,
+    file_size: Option<u64>,
+    sha256: Option<&'a str>,
+    checksum_error: Option<&'a str>,
+}
+
+/// Posting a grade this many hours after it was entered is reported as a delayed posting
+const POSTING_DELAY_THRESHOLD_HOURS: i64 = 1;
+
+fn format_duration(duration: chrono::Duration) -> String {
+    if duration.num_days() > 0 {
+        format!("{} days", duration.num_days())
+    } else if duration.num_hours() > 0 {
+        format!("{} hours", duration.num_hours())
+    } else {
+        format!("{} minutes", duration.num_minutes())
+    }
+}
+
+impl GradesCommand {
+    pub async fn action(&self, cfg: &Config) -> Result<(), anyhow::Error> {
+        match &self.action {
+            GradesAction::History(command) => command.action(cfg).await,
+            GradesAction::ExportMetadata(command) => command.action(cfg).await,
+        }
+    }
+}
+
+impl HistoryCommand {
+    pub async fn action(&self, cfg: &Config) -> Result<(), anyhow::Error> {
+        let NonEmptyConfig {
+            url: base_url,
+            access_token,
+        } = cfg.ensure_non_empty()?;
+
+        let client = reqwest::Client::builder()
+            .default_headers(
+                std::iter::once((
+                    reqwest::header::AUTHORIZATION,
+                    reqwest::header::HeaderValue::from_str(&format!("Bearer {}", access_token))
+                        .unwrap(),
+                ))
+                .collect(),
+            )
+            .build()
+            .unwrap();
+
+        let mut course_id = self.course.clone();
+        if let Ok(env_canvas_course_id) = std::env::var("CANVAS_COURSE_ID") {
+            course_id = Some(env_canvas_course_id.parse::<CanvasId>().unwrap())
+        }
+
+        let course = Course::fetch_with_colors(course_id.as_ref(), &base_url, &client).await?;
+
+        log::info!("Selected course {}", course.id);
+
+        let student = match &self.student {
+            Some(student) => student.path(),
+            None => client
+                .get(format!("{}/api/v1/users/self", base_url))
+                .send()
+                .await?
+                .json::<SelfResponse>()
+                .await?
+                .id
+                .to_string(),
+        };
+
+        let submissions = fetch_all_pages::<SubmissionResponse>(
+            &client,
+            &format!(
+                "{}/api/v1/courses/{}/students/submissions?student_ids[]={}&include[]=assignment&per_page=100",
+                base_url, course.id, student
+            ),
+        )
+        .await?;
+        log::info!("Made REST request to get submissions");
+
+        let grade_changes = fetch_grade_changes(
+            &client,
+            &format!(
+                "{}/api/v1/audit/grade_change/courses/{}/students/{}?per_page=100",
+                base_url, course.id, student
+            ),
+        )
+        .await?;
+
+        println!("✓ Queried grade information");
+
+        let assignment_names: HashMap<u32, String> = submissions
+            .iter()
+            .map(|submission| (submission.assignment_id, submission.assignment.name.clone()))
+            .collect();
+
+        let mut events: Vec<Event> = Vec::new();
+        for submission in submissions.iter() {
+            let assignment = submission.assignment.name.clone();
+
+            // the audit log records every grade entered, so only fall back to graded_at without it
+            if let (None, Some(graded_at)) = (&grade_changes, submission.graded_at) {
+                events.push(Event {
+                    at: graded_at,
+                    assignment: assignment.clone(),
+                    kind: EventKind::Graded {
+                        grade: submission.grade.clone(),
+                    },
+                });
+            }
+
+            if let Some(posted_at) = submission.posted_at {
+                let delay = submission
+                    .graded_at
+                    .map(|graded_at| posted_at - graded_at)
+                    .filter(|delay| {
+                        *delay > chrono::Duration::hours(POSTING_DELAY_THRESHOLD_HOURS)
+                    });
+                events.push(Event {
+                    at: posted_at,
+                    assignment,
+                    kind: EventKind::Posted { delay },
+                });
+            }
+        }
+
+        for grade_change in grade_changes.into_iter().flatten() {
+            let assignment = match grade_change.links.assignment {
+                Some(assignment) => assignment_names
+                    .get(&assignment)
+                    .cloned()
+                    .unwrap_or_else(|| format!("Assignment {}", assignment)),
+                None => "Overall grade".to_owned(),
+            };
+            events.push(Event {
+                at: grade_change.created_at,
+                assignment,
+                kind: match grade_change.grade_before {
+                    Some(before) if Some(&before) != grade_change.grade_after.as_ref() => {
+                        EventKind::Changed {
+                            before: Some(before),
+                            after: grade_change.grade_after,
+                        }
+                    }
+                    _ => EventKind::Graded {
+                        grade: grade_change.grade_after,
+                    },
+                },
+            });
+        }
+
+        if events.is_empty() {
+            println!("No grades posted yet");
+            return Ok(());
+        }
+
+        events.sort_by_key(|event| event.at);
+        for event in events.iter() {
+            println!("{event}");
+        }
+
+        Ok(())
+    }
+}
+
+impl ExportMetadataCommand {
+    pub async fn action(&self, cfg: &Config) -> Result<(), anyhow::Error> {
+        let NonEmptyConfig {
+            url: base_url,
+            access_token,
+        } = cfg.ensure_non_empty()?;
+
+        let client = reqwest::Client::builder()
+            .default_headers(
+                std::iter::once((
+                    reqwest::header::AUTHORIZATION,
+                    reqwest::header::HeaderValue::from_str(&format!("Bearer {}", access_token))
+                        .unwrap(),
+                ))
+                .collect(),
+            )
+            .build()
+            .unwrap();
+
+        let mut course_id = self.course.clone();
+        if let Ok(env_canvas_course_id) = std::env::var("CANVAS_COURSE_ID") {
+            course_id = Some(env_canvas_course_id.parse::<CanvasId>().unwrap())
+        }
+
+        let course = Course::fetch_with_colors(course_id.as_ref(), &base_url, &client).await?;
+
+        log::info!("Selected course {}", course.id);
+
+        let submissions = fetch_all_pages::<AssignmentSubmissionResponse>(
+            &client,
+            &format!(
+                "{}/api/v1/courses/{}/assignments/{}/submissions?include[]=submission_history&include[]=user&per_page=100",
+                base_url, course.id, self.assignment
+            ),
+        )
+        .await?;
+
+        println!("✓ Queried {} submissions", submissions.len());
+
+        // the same file shows up in every version of the submission history that kept it
+        let mut attachments: Vec<&AttachmentResponse> = submissions
+            .iter()
+            .flat_map(|submission| submission.submission_history.iter())
+            .flat_map(|attempt| attempt.attachments.iter())
+            .collect();
+        attachments.sort_by_key(|attachment| attachment.id);
+        attachments.dedup_by_key(|attachment| attachment.id);
+
+        let checksums: HashMap<u32, Result<String, String>> =
+            if self.no_checksums || attachments.is_empty() {
+                HashMap::new()
+            } else {
+                let spinner = Spinner::new(format!(
+                    "Computing checksums of {} files",
+                    attachments.len()
+                ));
+
+                let client = &client;
+                let checksums: HashMap<u32, Result<String, String>> =
+                    futures::stream::iter(attachments.iter())
+                        .map(|attachment| async move {
+                            (
+                                attachment.id,
+                                checksum(client, &attachment.url)
+                                    .await
+                                    .map_err(|error| error.to_string()),
+                            )
+                        })
+                        .buffer_unordered(4)
+                        .collect()
+                        .await;
+
+                let failed = checksums
+                    .values()
+                    .filter(|checksum| checksum.is_err())
+                    .count();
+                if failed > 0 {
+                    spinner.fail(format!(
+                        "Could not compute checksums of {} of {} files, see checksum_error",
+                        failed,
+                        checksums.len()
+                    ));
+                } else {
+                    spinner.finish(format!("Computed checksums of {} files", checksums.len()));
+                }
+                checksums
+            };
+
+        let records: Vec<SubmissionRecord> = submissions
+            .into_iter()
+            .map(|submission| SubmissionRecord {
+                user_id: submission.user_id,
+                user_name: submission.user.as_ref().map(|user| user.name.clone()),
+                sis_user_id: submission.user.and_then(|user| user.sis_user_id),
+                workflow_state: submission.workflow_state,
+                attempts: submission.attempt,
+                grade: submission.grade,
+                graded_at: submission.graded_at,
+                late: submission.late,
+                missing: submission.missing,
+                history: submission
+                    .submission_history
+                    .into_iter()
+                    .map(|attempt| AttemptRecord {
+                        attempt: attempt.attempt,
+                        submitted_at: attempt.submitted_at,
+                        late: attempt.late,
+                        seconds_late: attempt.seconds_late,
+                        files: attempt
+                            .attachments
+                            .into_iter()
+                            .map(|attachment| FileRecord {
+                                sha256: checksums
+                                    .get(&attachment.id)
+                                    .and_then(|checksum| checksum.clone().ok()),
+                                checksum_error: checksums
+                                    .get(&attachment.id)
+                                    .and_then(|checksum| checksum.clone().err()),
+                                id: attachment.id,
+                                name: attachment.display_name,
+                                size: attachment.size,
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let output = self.output.clone().unwrap_or_else(|| {
+            PathBuf::from(format!(
+                "submissions-{}.{}",
+                self.assignment,
+                match self.format {
+                    ExportFormat::Json => "json",
+                    ExportFormat::Csv => "csv",
+                }
+            ))
+        });
+
+        match self.format {
+            ExportFormat::Json => {
+                serde_json::to_writer_pretty(std::fs::File::create(&output)?, &records)?
+            }
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_path(&output)?;
+                for record in records.iter() {
+                    for row in submission_rows(record) {
+                        writer.serialize(row)?;
+                    }
+                }
+                writer.flush()?;
+            }
+        }
+
+        println!(
+            "✓ Exported metadata of {} submissions to {}",
+            records.len(),
+            output.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// Every page of the grade change audit log, which wraps its events in an object so it cannot use
+/// `fetch_all_pages`
+///
+/// The audit log is only available to users with permission to view it, so `None` without it.
+async fn fetch_grade_changes(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<Option<Vec<GradeChangeEventResponse>>, anyhow::Error> {
+    let mut response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        log::info!(
+            "Grade change audit log not available: {}",
+            response.status()
+        );
+        return Ok(None);
+    }
+
+    let mut events = Vec::new();
+    loop {
+        let next = next_page(response.headers());
+        events.extend(response.json::<GradeChangeResponse>().await?.events);
+        log::info!("Made REST request to get grade change audit log");
+
+        match next {
+            Some(url) => response = client.get(&url).send().await?.error_for_status()?,
+            None => return Ok(Some(events)),
+        }
+    }
+}
+
+/// SHA-256 of a file, streamed so large submissions are not held in memory
+async fn checksum(client: &reqwest::Client, url: &str) -> Result<String, anyhow::Error> {
+    let mut response = client.get(url).send().await?.error_for_status()?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn submission_row<'a>(
+    record: &'a SubmissionRecord,
+    attempt: Option<&'a AttemptRecord>,
+    file: Option<&'a FileRecord>,
+) -> SubmissionRow<'a> {
+    SubmissionRow {
+        user_id: record.user_id,
+        user_name: record.user_name.as_deref(),
+        sis_user_id: record.sis_user_id.as_deref(),
+        workflow_state: &record.workflow_state,
+        attempts: record.attempts,
+        grade: record.grade.as_deref(),
+        graded_at: record.graded_at,
+        late: record.late,
+        missing: record.missing,
+        attempt: attempt.and_then(|attempt| attempt.attempt),
+        submitted_at: attempt.and_then(|attempt| attempt.submitted_at),
+        attempt_late: attempt.is_some_and(|attempt| attempt.late),
+        seconds_late: attempt.map_or(0, |attempt| attempt.seconds_late),
+        file_id: file.map(|file| file.id),
+        file_name: file.map(|file| file.name.as_str()),
+        file_size: file.map(|file| file.size),
+        sha256: file.and_then(|file| file.sha256.as_deref()),
+        checksum_error: file.and_then(|file| file.checksum_error.as_deref()),
+    }
+}
+
+fn submission_rows(record: &SubmissionRecord) -> Vec<SubmissionRow<'_>> {
+    if record.history.is_empty() {
+        return vec![submission_row(record, None, None)];
+    }
+
+    record
+        .history
+        .iter()
+        .flat_map(|attempt| {
+            if attempt.files.is_empty() {
+                vec![submission_row(record, Some(attempt), None)]
+            } else {
+                attempt
+                    .files
+                    .iter()
+                    .map(|file| submission_row(record, Some(attempt), Some(file)))
+                    .collect()
+            }
+        })
+        .collect()
+}
@@ -1,15 +1,97 @@
 use colored::Colorize;
 use inquire::Select;
-use reqwest::Client;
+use reqwest::{header::HeaderMap, Client};
+use serde::de::DeserializeOwned;
 use serde_derive::Deserialize;
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, str::FromStr, time::Duration};
+use tokio::sync::OnceCell;
+
+pub mod progress;
+pub mod upload;
 
 pub type DateTime = chrono::DateTime<chrono::Utc>;
 
+/// Course colors are only decoration, so give up on them rather than hold up the command
+const COLORS_TIMEOUT: Duration = Duration::from_secs(2);
+
+static COURSE_COLORS: OnceCell<HashMap<u32, String>> = OnceCell::const_new();
+
+/// Identifier for a Canvas object, either a numeric Canvas ID or an SIS ID such as
+/// `sis_course_id:MATH-101` which Canvas accepts anywhere the numeric ID is
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum CanvasId {
+    Id(u32),
+    Sis { kind: String, value: String },
+}
+
+impl CanvasId {
+    /// Form of the ID to use in a URL path or query, SIS values need to be percent encoded
+    pub fn path(&self) -> String {
+        match self {
+            CanvasId::Id(id) => id.to_string(),
+            CanvasId::Sis { kind, value } => format!(
+                "{}:{}",
+                kind,
+                value
+                    .bytes()
+                    .map(|b| match b {
+                        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'~' => {
+                            (b as char).to_string()
+                        }
+                        _ => format!("%{:02X}", b),
+                    })
+                    .collect::<String>()
+            ),
+        }
+    }
+}
+
+impl FromStr for CanvasId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(id) = s.parse::<u32>() {
+            return Ok(CanvasId::Id(id));
+        }
+
+        match s.split_once(':') {
+            Some((kind, value)) if kind.starts_with("sis_") && !value.is_empty() => {
+                Ok(CanvasId::Sis {
+                    kind: kind.to_owned(),
+                    value: value.to_owned(),
+                })
+            }
+            _ => Err(format!(
+                "{} is neither a Canvas ID nor an SIS ID such as sis_course_id:MATH-101",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for CanvasId {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl Display for CanvasId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CanvasId::Id(id) => write!(f, "{}", id),
+            CanvasId::Sis { kind, value } => write!(f, "{}:{}", kind, value),
+        }
+    }
+}
+
 #[derive(Debug, Hash, Clone, PartialEq, Eq)]
 pub struct Course {
     pub name: String,
     pub id: u32,
+    pub course_code: String,
     is_favorite: bool,
     css_color: Option<String>,
     created_at: DateTime,
@@ -19,8 +101,11 @@ pub struct Course {
 struct CourseResponse {
     id: u32,
     name: String,
+    course_code: String,
+    #[serde(default)]
     is_favorite: bool,
     created_at: DateTime,
+    #[serde(default)]
     concluded: bool,
 }
 
@@ -31,90 +116,226 @@ struct ColorsResponse {
 
 impl Display for Course {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let css_color = self.css_color.clone().unwrap_or("#000000".to_string());
-        let color = csscolorparser::parse(&css_color)
-            .unwrap()
-            .to_linear_rgba_u8();
+        let swatch = match self
+            .css_color
+            .as_deref()
+            .and_then(|css_color| csscolorparser::parse(css_color).ok())
+        {
+            Some(color) => {
+                let color = color.to_linear_rgba_u8();
+                "█ ".truecolor(color.0, color.1, color.2)
+            }
+            None => "█ ".normal(),
+        };
         write!(
             f,
             "{}{}{}",
-            "█ ".truecolor(color.0, color.1, color.2),
+            swatch,
             self.name,
             if self.is_favorite { " ★" } else { "" }.yellow()
         )
     }
 }
 
+/// URL of the next page from the `Link` header of a paginated Canvas response
+pub fn next_page(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(reqwest::header::LINK)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .find_map(|link| {
+            let (url, params) = link.split_once(';')?;
+            params
+                .split(';')
+                .any(|param| param.trim() == r#"rel="next""#)
+                .then(|| {
+                    url.trim()
+                        .trim_start_matches('<')
+                        .trim_end_matches('>')
+                        .to_owned()
+                })
+        })
+}
+
+/// Fetch every page of a paginated Canvas list by following the `Link: rel="next"` headers
+pub async fn fetch_all_pages<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+) -> Result<Vec<T>, anyhow::Error> {
+    let mut items = Vec::new();
+    let mut next = Some(url.to_owned());
+    while let Some(url) = next {
+        let response = client.get(&url).send().await?.error_for_status()?;
+        next = next_page(response.headers());
+        items.extend(response.json::<Vec<T>>().await?);
+        log::info!("Made REST request to get page {}", url);
+    }
+    Ok(items)
+}
+
+async fn fetch_course_colors(
+    base_url: &str,
+    client: &Client,
+) -> Result<HashMap<u32, String>, anyhow::Error> {
+    let colors = client
+        .get(format!("{}/api/v1/users/self/colors", base_url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<ColorsResponse>()
+        .await?
+        .custom_colors
+        .into_iter()
+        .filter_map(|(k, v)| Some((k.strip_prefix("course_")?.parse::<u32>().ok()?, v)))
+        .collect();
+    log::info!("Made REST request to get course colors");
+
+    Ok(colors)
+}
+
+/// Custom course colors of the user, fetched at most once since each run talks to a single Canvas
+///
+/// Empty when colored output is disabled, or when the colors endpoint fails or is too slow.
+pub async fn course_colors(base_url: &str, client: &Client) -> &'static HashMap<u32, String> {
+    COURSE_COLORS
+        .get_or_init(|| async {
+            if !colored::control::SHOULD_COLORIZE.should_colorize() {
+                return HashMap::new();
+            }
+
+            match tokio::time::timeout(COLORS_TIMEOUT, fetch_course_colors(base_url, client)).await
+            {
+                Ok(Ok(colors)) => colors,
+                Ok(Err(error)) => {
+                    log::warn!("Failed to get course colors: {}", error);
+                    HashMap::new()
+                }
+                Err(_) => {
+                    log::warn!("Timed out getting course colors");
+                    HashMap::new()
+                }
+            }
+        })
+        .await
+}
+
 impl Course {
+    /// Fetch a public course without authentication, so there are no favorites or colors
+    pub async fn fetch_public(
+        course_id: &CanvasId,
+        base_url: &str,
+        client: &Client,
+    ) -> Result<Course, anyhow::Error> {
+        let course_response = client
+            .get(format!("{}/api/v1/courses/{}", base_url, course_id.path()))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<CourseResponse>()
+            .await?;
+        log::info!("Made REST request to get public course information");
+
+        let course = Course {
+            name: course_response.name,
+            id: course_response.id,
+            course_code: course_response.course_code,
+            is_favorite: false,
+            css_color: None,
+            created_at: course_response.created_at,
+        };
+
+        println!("✓ Found {course}");
+        Ok(course)
+    }
+
+    /// Fetch a course, or let the user pick one of their active courses, without course colors
     pub async fn fetch(
-        course_id: Option<u32>,
+        course_id: Option<&CanvasId>,
+        base_url: &str,
+        client: &Client,
+    ) -> Result<Course, anyhow::Error> {
+        Course::fetch_colored(course_id, base_url, client, false).await
+    }
+
+    /// Same as `fetch`, but also shows the custom course colors of the user, which are requested
+    /// alongside the course and given up on after a timeout
+    pub async fn fetch_with_colors(
+        course_id: Option<&CanvasId>,
         base_url: &str,
         client: &Client,
     ) -> Result<Course, anyhow::Error> {
+        Course::fetch_colored(course_id, base_url, client, true).await
+    }
+
+    async fn fetch_colored(
+        course_id: Option<&CanvasId>,
+        base_url: &str,
+        client: &Client,
+        colors: bool,
+    ) -> Result<Course, anyhow::Error> {
+        let course_colors = async {
+            if colors {
+                Some(course_colors(base_url, client).await)
+            } else {
+                None
+            }
+        };
+
         Ok(if let Some(course_id) = course_id {
-            let course_response = client
-                .get(format!(
-                    "{}/api/v1/courses/{}?include[]=favorites&include[]=concluded",
-                    base_url, course_id
-                ))
-                .send()
-                .await?
-                .json::<CourseResponse>()
-                .await?;
+            let (course_response, course_colors) = tokio::join!(
+                async {
+                    client
+                        .get(format!(
+                            "{}/api/v1/courses/{}?include[]=favorites&include[]=concluded",
+                            base_url,
+                            course_id.path()
+                        ))
+                        .send()
+                        .await?
+                        .json::<CourseResponse>()
+                        .await
+                },
+                course_colors
+            );
+            let course_response = course_response?;
             log::info!("Made REST request to get course information");
 
-            let course_colors: HashMap<u32, String> = client
-                .get(format!("{}/api/v1/users/self/colors", base_url))
-                .send()
-                .await?
-                .json::<ColorsResponse>()
-                .await?
-                .custom_colors
-                .into_iter()
-                .filter(|(k, _)| k.starts_with("course_"))
-                .map(|(k, v)| (k.trim_start_matches("course_").parse::<u32>().unwrap(), v))
-                .collect();
-            log::info!("Made REST request to get course colors");
-
             let course = Course {
                 name: course_response.name,
                 id: course_response.id,
+                course_code: course_response.course_code,
                 is_favorite: course_response.is_favorite,
-                css_color: course_colors.get(&course_response.id).cloned(),
+                css_color: course_colors
+                    .and_then(|colors| colors.get(&course_response.id))
+                    .cloned(),
                 created_at: course_response.created_at,
             };
 
             println!("✓ Found {course}");
             course
         } else {
-            let courses_response = client
-                .get(format!(
-                    "{}/api/v1/courses?per_page=1000&include[]=favorites&include[]=concluded",
-                    base_url
-                ))
-                .send()
-                .await?
-                .json::<Vec<serde_json::Value>>()
-                .await?
+            let (courses_response, course_colors) = tokio::join!(
+                async {
+                    client
+                        .get(format!(
+                            "{}/api/v1/courses?per_page=1000&include[]=favorites&include[]=concluded",
+                            base_url
+                        ))
+                        .send()
+                        .await?
+                        .json::<Vec<serde_json::Value>>()
+                        .await
+                },
+                course_colors
+            );
+            let courses_response = courses_response?
                 .into_iter()
                 .filter_map(|v| serde_json::from_value(v).ok())
                 .collect::<Vec<CourseResponse>>();
 
             log::info!("Made REST request to get favorite courses");
 
-            let course_colors: HashMap<u32, String> = client
-                .get(format!("{}/api/v1/users/self/colors", base_url))
-                .send()
-                .await?
-                .json::<ColorsResponse>()
-                .await?
-                .custom_colors
-                .into_iter()
-                .filter(|(k, _)| k.starts_with("course_"))
-                .map(|(k, v)| (k.trim_start_matches("course_").parse::<u32>().unwrap(), v))
-                .collect();
-            log::info!("Made REST request to get course colors");
-
             println!("✓ Queried course information");
 
             let mut courses: Vec<Course> = courses_response
@@ -123,8 +344,11 @@ impl Course {
                 .map(|course| Course {
                     name: course.name.clone(),
                     id: course.id,
+                    course_code: course.course_code.clone(),
                     is_favorite: course.is_favorite,
-                    css_color: course_colors.get(&course.id).cloned(),
+                    css_color: course_colors
+                        .and_then(|colors| colors.get(&course.id))
+                        .cloned(),
                     created_at: course.created_at,
                 })
                 .collect();
@@ -138,3 +362,96 @@ impl Course {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canvas_id_parses_numeric_ids() {
+        let id = "12345".parse::<CanvasId>().unwrap();
+        assert_eq!(id, CanvasId::Id(12345));
+        assert_eq!(id.path(), "12345");
+        assert_eq!(id.to_string(), "12345");
+    }
+
+    #[test]
+    fn canvas_id_parses_sis_ids() {
+        let id = "sis_course_id:MATH 101/A".parse::<CanvasId>().unwrap();
+        assert_eq!(
+            id,
+            CanvasId::Sis {
+                kind: "sis_course_id".to_owned(),
+                value: "MATH 101/A".to_owned(),
+            }
+        );
+        assert_eq!(id.to_string(), "sis_course_id:MATH 101/A");
+    }
+
+    #[test]
+    fn canvas_id_percent_encodes_sis_values() {
+        let cases = [
+            ("sis_course_id:MATH-101_a~b", "sis_course_id:MATH-101_a~b"),
+            ("sis_course_id:MATH 101/A", "sis_course_id:MATH%20101%2FA"),
+            ("sis_course_id:2024.FALL", "sis_course_id:2024%2EFALL"),
+            ("sis_course_id:Café", "sis_course_id:Caf%C3%A9"),
+            ("sis_user_id:a:b", "sis_user_id:a%3Ab"),
+        ];
+        for (input, path) in cases {
+            assert_eq!(input.parse::<CanvasId>().unwrap().path(), path);
+        }
+    }
+
+    #[test]
+    fn next_page_follows_rel_next() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::LINK,
+            r#"<https://canvas.test/api/v1/x?page=1>; rel="current", <https://canvas.test/api/v1/x?page=2>; rel="next", <https://canvas.test/api/v1/x?page=5>; rel="last""#
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            next_page(&headers).as_deref(),
+            Some("https://canvas.test/api/v1/x?page=2")
+        );
+
+        headers.insert(
+            reqwest::header::LINK,
+            r#"<https://canvas.test/api/v1/x?page=5>; rel="current", <https://canvas.test/api/v1/x?page=5>; rel="last""#
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(next_page(&headers), None);
+        assert_eq!(next_page(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn canvas_id_rejects_invalid_ids() {
+        for input in [
+            "",
+            "sis_course_id:",
+            "MATH-101",
+            "course_id:MATH-101",
+            ":MATH-101",
+        ] {
+            assert!(input.parse::<CanvasId>().is_err(), "{input} was accepted");
+        }
+    }
+
+    #[test]
+    fn course_display_tolerates_unparsable_colors() {
+        colored::control::set_override(true);
+        for css_color in ["var(--x)", "", "#zzz", "rgb(300"] {
+            let course = Course {
+                name: "Sandbox".to_owned(),
+                id: 1,
+                course_code: "SANDBOX".to_owned(),
+                is_favorite: false,
+                css_color: Some(css_color.to_owned()),
+                created_at: chrono::Utc::now(),
+            };
+            assert!(course.to_string().contains("Sandbox"));
+        }
+    }
+}
@@ -1,16 +1,37 @@
 use anyhow::anyhow;
 use clap::{CommandFactory, Parser, Subcommand};
 use serde_derive::{Deserialize, Serialize};
-use std::env;
+use std::{env, path::PathBuf};
 
+pub mod admin;
 pub mod auth;
 pub mod download;
+pub mod files;
+pub mod grades;
+pub mod selftest;
 pub mod submit;
+pub mod tail;
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Config {
     url: Option<String>,
     access_token: Option<String>,
+    /// Downloads without a --directory go into a directory per course inside this one, which
+    /// must be absolute or start with ~
+    download_directory: Option<PathBuf>,
+    /// How the directory per course inside download_directory is named
+    #[serde(default)]
+    download_naming: DownloadNaming,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadNaming {
+    /// Course code, such as MATH 101
+    #[default]
+    CourseCode,
+    /// Course name, which is your nickname for the course if you set one
+    Nickname,
 }
 
 #[derive(Debug)]
@@ -25,6 +46,7 @@ impl Config {
             Self {
                 url: Some(url),
                 access_token: Some(access_token),
+                ..
             } => Ok(NonEmptyConfig {
                 url: url.clone(),
                 access_token: access_token.clone(),
@@ -50,8 +72,14 @@ struct Args {
 #[derive(Subcommand, Debug)]
 enum Action {
     Auth(auth::AuthCommand),
+    Admin(admin::AdminCommand),
     Submit(submit::SubmitCommand),
     Download(download::DownloadCommand),
+    Files(files::FilesCommand),
+    #[command(visible_alias = "grade")]
+    Grades(grades::GradesCommand),
+    Tail(tail::TailCommand),
+    Selftest(selftest::SelftestCommand),
 
     /// Generate shell completions
     Completions {
@@ -84,8 +112,13 @@ async fn main() -> Result<(), anyhow::Error> {
 
     match args.action {
         Action::Auth(command) => command.action(&mut cfg).await,
+        Action::Admin(command) => command.action(&cfg).await,
         Action::Submit(command) => command.action(&cfg).await,
         Action::Download(command) => command.action(&cfg).await,
+        Action::Files(command) => command.action(&cfg).await,
+        Action::Grades(command) => command.action(&cfg).await,
+        Action::Tail(command) => command.action(&cfg).await,
+        Action::Selftest(command) => command.action(&cfg).await,
 
         Action::Completions { shell } => unreachable!(),
     }
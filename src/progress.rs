@@ -0,0 +1,73 @@
+use std::{borrow::Cow, io::IsTerminal, time::Duration};
+
+use colored::Colorize;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+/// Spinner for a long running step, finished with a ✓ or a red ✗
+///
+/// When stderr is not a terminal nothing is animated and only the final line is printed.
+/// A spinner dropped before it is finished is marked as failed, so errors propagated with `?`
+/// do not leave a spinner running.
+pub struct Spinner {
+    bar: ProgressBar,
+    is_terminal: bool,
+}
+
+impl Spinner {
+    pub fn new(message: impl Into<Cow<'static, str>>) -> Spinner {
+        Spinner::from_bar(ProgressBar::new_spinner(), message)
+    }
+
+    /// Spinner drawn as one line of `multi_progress`
+    pub fn new_in(
+        multi_progress: &MultiProgress,
+        message: impl Into<Cow<'static, str>>,
+    ) -> Spinner {
+        Spinner::from_bar(multi_progress.add(ProgressBar::new_spinner()), message)
+    }
+
+    fn from_bar(bar: ProgressBar, message: impl Into<Cow<'static, str>>) -> Spinner {
+        let is_terminal = std::io::stderr().is_terminal();
+        bar.set_message(message);
+        if is_terminal {
+            bar.enable_steady_tick(Duration::from_millis(100));
+        }
+        Spinner { bar, is_terminal }
+    }
+
+    pub fn set_message(&self, message: impl Into<Cow<'static, str>>) {
+        self.bar.set_message(message);
+    }
+
+    pub fn finish(self, message: impl Into<Cow<'static, str>>) {
+        let message = message.into();
+        if !self.is_terminal {
+            println!("✓ {}", message);
+        }
+        self.bar
+            .set_style(ProgressStyle::with_template("✓ {wide_msg}").unwrap());
+        self.bar.finish_with_message(message);
+    }
+
+    pub fn fail(self, message: impl Into<Cow<'static, str>>) {
+        self.fail_in_place(message.into());
+    }
+
+    fn fail_in_place(&self, message: Cow<'static, str>) {
+        if !self.is_terminal {
+            eprintln!("{} {}", "✗".red(), message);
+        }
+        self.bar
+            .set_style(ProgressStyle::with_template("{prefix:.red} {wide_msg}").unwrap());
+        self.bar.set_prefix("✗");
+        self.bar.abandon_with_message(message);
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        if !self.bar.is_finished() {
+            self.fail_in_place(self.bar.message().into());
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{
+    submit::{upload_file, Assignment},
+    Config, NonEmptyConfig,
+};
+use anyhow::anyhow;
+use canvas_cli::{fetch_all_pages, progress::Spinner, CanvasId, Course};
+use indicatif::MultiProgress;
+use inquire::Confirm;
+use serde_derive::Deserialize;
+use sha2::{Digest, Sha256};
+
+#[derive(clap::Parser, Debug)]
+/// Check that the main flows work against a sandbox course
+///
+/// Lists the courses and the files of the sandbox course, submits a tiny generated file to a test
+/// assignment, downloads the submission back and compares checksums.
+pub struct SelftestCommand {
+    /// Canvas course ID or SIS course ID (sis_course_id:...) of the sandbox course
+    #[clap(long, short)]
+    course: CanvasId,
+
+    /// Canvas assignment ID of the test assignment
+    #[clap(long, short)]
+    assignment: Option<u32>,
+
+    /// Do not ask for confirmation before submitting
+    #[clap(long, short)]
+    yes: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct ListedCourseResponse {
+    id: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct AttachmentResponse {
+    display_name: String,
+    url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SubmissionResponse {
+    #[serde(default)]
+    attachments: Vec<AttachmentResponse>,
+}
+
+impl SelftestCommand {
+    pub async fn action(&self, cfg: &Config) -> Result<(), anyhow::Error> {
+        let NonEmptyConfig {
+            url: base_url,
+            access_token,
+        } = cfg.ensure_non_empty()?;
+
+        let client = reqwest::Client::builder()
+            .default_headers(
+                std::iter::once((
+                    reqwest::header::AUTHORIZATION,
+                    reqwest::header::HeaderValue::from_str(&format!("Bearer {}", access_token))
+                        .unwrap(),
+                ))
+                .collect(),
+            )
+            .build()
+            .unwrap();
+
+        let course = Course::fetch(Some(&self.course), &base_url, &client).await?;
+
+        log::info!("Selected course {}", course.id);
+
+        let spinner = Spinner::new("Listing courses");
+        let courses = fetch_all_pages::<ListedCourseResponse>(
+            &client,
+            &format!("{}/api/v1/courses?per_page=100", base_url),
+        )
+        .await?;
+        if !courses.iter().any(|listed| listed.id == course.id) {
+            spinner.fail(format!(
+                "Listed {} courses without {}",
+                courses.len(),
+                course.name
+            ));
+            return Err(anyhow!("Course listing does not contain {}", course.name));
+        }
+        spinner.finish(format!("Listed {} courses", courses.len()));
+
+        let spinner = Spinner::new(format!("Listing files of {}", course.name));
+        let files = fetch_all_pages::<serde::de::IgnoredAny>(
+            &client,
+            &format!(
+                "{}/api/v1/courses/{}/files?per_page=100",
+                base_url, course.id
+            ),
+        )
+        .await?;
+        spinner.finish(format!("Listed {} files of {}", files.len(), course.name));
+
+        let assignment = Assignment::fetch(self.assignment, &base_url, &course, &client).await?;
+
+        log::info!("Selected assignment {}", assignment.id);
+
+        if !self.yes
+            && !Confirm::new(&format!(
+                "Submit a test file to {} in {}?",
+                assignment.name, course.name
+            ))
+            .with_default(false)
+            .with_help_message("Only use a sandbox course, this makes a real submission")
+            .prompt()?
+        {
+            println!("Self test cancelled");
+            return Ok(());
+        }
+
+        let timestamp = chrono::Utc::now();
+        let filename = format!("canvas-cli-selftest-{}.txt", timestamp.timestamp());
+        let path = std::env::temp_dir().join(&filename);
+        let content = format!("canvas-cli self test at {}\n", timestamp.to_rfc3339());
+        std::fs::write(&path, &content)?;
+        let checksum = format!("{:x}", Sha256::digest(content.as_bytes()));
+        println!("✓ Created test file {}", path.display());
+
+        let multi_progress = MultiProgress::new();
+        let uploaded = upload_file(
+            &base_url,
+            &course,
+            &assignment,
+            &client,
+            &path.to_string_lossy(),
+            &multi_progress,
+        )
+        .await;
+        std::fs::remove_file(&path)?;
+        let uploaded = uploaded?;
+
+        let spinner = Spinner::new(format!("Submitting {}", filename));
+        client
+            .post(format!(
+                "{}/api/v1/courses/{}/assignments/{}/submissions",
+                base_url, course.id, assignment.id
+            ))
+            .query(&[
+                ("submission[file_ids][]", uploaded.id.to_string()),
+                ("submission[submission_type]", "online_upload".to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+        spinner.finish(format!("Submitted {}", filename));
+
+        let spinner = Spinner::new(format!("Downloading {} from submission", filename));
+        let submission = client
+            .get(format!(
+                "{}/api/v1/courses/{}/assignments/{}/submissions/self",
+                base_url, course.id, assignment.id
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<SubmissionResponse>()
+            .await?;
+        log::info!("Made REST request to get submission");
+
+        let attachment = submission
+            .attachments
+            .iter()
+            .find(|attachment| attachment.display_name == filename)
+            .ok_or_else(|| anyhow!("Submission does not contain {}", filename))?;
+
+        let mut response = client
+            .get(&attachment.url)
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = response.chunk().await? {
+            hasher.update(&chunk);
+        }
+        spinner.finish(format!("Downloaded {} from submission", filename));
+
+        let downloaded_checksum = format!("{:x}", hasher.finalize());
+        if downloaded_checksum != checksum {
+            Err(anyhow!(
+                "Checksum mismatch, uploaded {} but downloaded {}",
+                checksum,
+                downloaded_checksum
+            ))?;
+        }
+        println!("✓ Checksums match ({})", checksum);
+
+        println!("✓ Self test passed 🎉");
+
+        Ok(())
+    }
+}
@@ -1,23 +1,23 @@
-use std::{collections::HashMap, fmt::Display};
+use std::fmt::Display;
 
 use crate::{Config, NonEmptyConfig};
 use anyhow::anyhow;
-use canvas_cli::{Course, DateTime};
+use canvas_cli::{
+    progress::Spinner,
+    upload::{self, UploadResponse},
+    CanvasId, Course, DateTime,
+};
 use fuzzy_matcher::FuzzyMatcher;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use indicatif::MultiProgress;
 use inquire::Select;
 use regex::Regex;
-use reqwest::{
-    multipart::{Form, Part},
-    Body, Client,
-};
+use reqwest::Client;
 use serde_derive::Deserialize;
-use tokio_util::codec::{BytesCodec, FramedRead};
 
 #[derive(Debug)]
-struct Assignment {
-    id: u32,
-    name: String,
+pub struct Assignment {
+    pub id: u32,
+    pub name: String,
     due_at: Option<DateTime>,
     is_graded: bool,
 }
@@ -28,6 +28,70 @@ impl Display for Assignment {
     }
 }
 
+impl Assignment {
+    pub async fn fetch(
+        assignment_id: Option<u32>,
+        base_url: &str,
+        course: &Course,
+        client: &Client,
+    ) -> Result<Assignment, anyhow::Error> {
+        Ok(if let Some(assignment_id) = assignment_id {
+            let assignment_response = client
+                .get(format!(
+                    "{}/api/v1/courses/{}/assignments/{}",
+                    base_url, course.id, assignment_id
+                ))
+                .send()
+                .await?
+                .json::<AssignmentResponse>()
+                .await?;
+            log::info!("Made REST request to get assignment information");
+
+            let assignment = Assignment {
+                name: assignment_response.name,
+                id: assignment_response.id,
+                due_at: assignment_response.due_at,
+                is_graded: assignment_response.graded_submissions_exist,
+            };
+
+            println!("✓ Found {assignment}");
+
+            assignment
+        } else {
+            let mut assignments: Vec<Assignment> = client
+                .get(format!(
+                    "{}/api/v1/courses/{}/assignments?per_page=1000",
+                    base_url, course.id
+                ))
+                .send()
+                .await?
+                .json::<Vec<AssignmentResponse>>()
+                .await?
+                .into_iter()
+                .filter(|assignment| {
+                    !assignment.locked_for_user && assignment.submission_types[0] == "online_upload"
+                })
+                .map(|assignment| Assignment {
+                    name: assignment.name,
+                    id: assignment.id,
+                    due_at: assignment.due_at,
+                    is_graded: assignment.graded_submissions_exist,
+                })
+                .collect();
+            log::info!("Made REST request to get assignment information");
+            println!("✓ Queried assignment information");
+
+            assignments.sort_by(|a, b| a.is_graded.cmp(&b.is_graded).then(a.due_at.cmp(&b.due_at)));
+            let matcher = fuzzy_matcher::skim::SkimMatcherV2::default();
+            Select::new("Assignment?", assignments)
+                .with_filter(&|input, _, string_value, _| {
+                    matcher.fuzzy_match(string_value, input).is_some()
+                })
+                .prompt()?
+        })
+    }
+}
+
 #[derive(Deserialize, Debug)]
 struct AssignmentResponse {
     id: u32,
@@ -38,18 +102,6 @@ struct AssignmentResponse {
     submission_types: Vec<String>,
 }
 
-#[derive(Deserialize, Debug)]
-struct UploadBucket {
-    upload_url: String,
-    upload_params: HashMap<String, String>,
-}
-
-#[derive(Deserialize, Debug)]
-struct UploadResponse {
-    id: u32,
-    display_name: Option<String>,
-}
-
 #[derive(clap::Parser, Debug)]
 /// Submit Canvas assignment
 pub struct SubmitCommand {
@@ -60,9 +112,9 @@ pub struct SubmitCommand {
     #[clap(long, short)]
     url: Option<String>,
 
-    /// Canvas course ID
+    /// Canvas course ID or SIS course ID (sis_course_id:...)
     #[clap(long, short)]
-    course: Option<u32>,
+    course: Option<CanvasId>,
 
     /// Canvas assignment ID
     #[clap(long, short)]
@@ -104,7 +156,7 @@ impl SubmitCommand {
             .build()
             .unwrap();
 
-        let mut course_id = self.course;
+        let mut course_id = self.course.clone();
         let mut assignment_id = self.assignment;
         let canvas_assignment_url = if let Ok(env_canvas_url) = std::env::var("CANVAS_URL") {
             Some(env_canvas_url)
@@ -117,14 +169,16 @@ impl SubmitCommand {
 
             let captures = regex.captures(&canvas_assignment_url).unwrap();
             base_url = captures.get(1).unwrap().as_str().to_string();
-            course_id = Some(captures.get(2).unwrap().as_str().parse::<u32>().unwrap());
+            course_id = Some(CanvasId::Id(
+                captures.get(2).unwrap().as_str().parse::<u32>().unwrap(),
+            ));
             if let Some(a_id) = captures.get(3) {
                 assignment_id = Some(a_id.as_str().parse::<u32>().unwrap());
             }
         }
 
         if let Ok(env_canvas_course_id) = std::env::var("CANVAS_COURSE_ID") {
-            course_id = Some(env_canvas_course_id.parse::<u32>().unwrap())
+            course_id = Some(env_canvas_course_id.parse::<CanvasId>().unwrap())
         }
 
         if let Ok(env_canvas_assignment_id) = std::env::var("CANVAS_ASSIGNMENT_ID") {
@@ -135,64 +189,11 @@ impl SubmitCommand {
         let course_id = course_id;
         let assignment_id = assignment_id;
 
-        let course = Course::fetch(course_id, &base_url, &client).await?;
+        let course = Course::fetch_with_colors(course_id.as_ref(), &base_url, &client).await?;
 
         log::info!("Selected course {}", course.id);
 
-        let assignment = if let Some(assignment_id) = assignment_id {
-            let assignment_response = client
-                .get(format!(
-                    "{}/api/v1/courses/{}/assignments/{}",
-                    base_url, course.id, assignment_id
-                ))
-                .send()
-                .await?
-                .json::<AssignmentResponse>()
-                .await?;
-            log::info!("Made REST request to get assignment information");
-
-            let assignment = Assignment {
-                name: assignment_response.name,
-                id: assignment_response.id,
-                due_at: assignment_response.due_at,
-                is_graded: assignment_response.graded_submissions_exist,
-            };
-
-            println!("✓ Found {assignment}");
-
-            assignment
-        } else {
-            let mut assignments: Vec<Assignment> = client
-                .get(format!(
-                    "{}/api/v1/courses/{}/assignments?per_page=1000",
-                    base_url, course.id
-                ))
-                .send()
-                .await?
-                .json::<Vec<AssignmentResponse>>()
-                .await?
-                .into_iter()
-                .filter(|assignment| {
-                    !assignment.locked_for_user && assignment.submission_types[0] == "online_upload"
-                })
-                .map(|assignment| Assignment {
-                    name: assignment.name,
-                    id: assignment.id,
-                    due_at: assignment.due_at,
-                    is_graded: assignment.graded_submissions_exist,
-                })
-                .collect();
-            log::info!("Made REST request to get assignment information");
-            println!("✓ Queried assignment information");
-
-            assignments.sort_by(|a, b| a.is_graded.cmp(&b.is_graded).then(a.due_at.cmp(&b.due_at)));
-            let matcher = fuzzy_matcher::skim::SkimMatcherV2::default();
-            Select::new("Assignment?", assignments)
-                .with_filter(&|input, _, string_value, _| {
-                    matcher.fuzzy_match(string_value, input).is_some()
-                })
-                .prompt()?
-        };
+        let assignment = Assignment::fetch(assignment_id, &base_url, &course, &client).await?;
 
         log::info!("Selected assignment {}", assignment.id);
 
@@ -208,15 +209,13 @@ impl SubmitCommand {
             )
         });
 
-        let uploaded_files = futures::future::join_all(future_files).await;
+        let uploaded_files = futures::future::join_all(future_files)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<UploadResponse>, anyhow::Error>>()?;
         let mut params: Vec<(String, String)> = uploaded_files
             .into_iter()
-            .map(|f| {
-                (
-                    "submission[file_ids][]".to_string(),
-                    f.unwrap().id.to_string(),
-                )
-            })
+            .map(|f| ("submission[file_ids][]".to_string(), f.id.to_string()))
             .collect();
         params.push((
             "submission[submission_type]".to_string(),
@@ -242,7 +241,7 @@ impl SubmitCommand {
     }
 }
 
-async fn upload_file(
+pub async fn upload_file(
     url: &str,
     course: &Course,
     assignment: &Assignment,
@@ -250,84 +249,25 @@ async fn upload_file(
     filepath: &str,
     multi_progress: &MultiProgress,
 ) -> Result<UploadResponse, anyhow::Error> {
-    let metadata = std::fs::metadata(filepath).unwrap();
-    let path = std::path::Path::new(filepath);
-    let file = tokio::fs::File::open(path).await.unwrap();
-    let basename = path.file_name().unwrap().to_str().unwrap();
-
-    let spinner = multi_progress.add(ProgressBar::new_spinner());
-    spinner.set_message(format!("Uploading file {} as {}", filepath, basename));
-
-    let spinner_clone = spinner.clone();
-    let spinner_task = tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-            spinner_clone.inc(1);
-        }
-    });
+    let spinner = Spinner::new_in(multi_progress, format!("Uploading file {}", filepath));
 
-    let upload_bucket = client
-        .post(format!(
+    let upload_response = upload::upload_file(
+        client,
+        &format!(
             "{}/api/v1/courses/{}/assignments/{}/submissions/self/files",
             url, course.id, assignment.id
-        ))
-        .form(&HashMap::from([
-            ("name", basename),
-            ("size", metadata.len().to_string().as_str()),
-        ]))
-        .send()
-        .await?
-        .json::<UploadBucket>()
-        .await
-        .unwrap();
-
-    spinner.set_message(format!(
-        "Uploading {}: recieved upload bucket, sending file payload",
-        filepath
-    ));
-
-    let location = client
-        .post(upload_bucket.upload_url)
-        .multipart(
-            upload_bucket
-                .upload_params
-                .into_iter()
-                .fold(Form::new(), |form, (k, v)| form.text(k, v))
-                .part(
-                    "file",
-                    Part::stream(Body::wrap_stream(FramedRead::new(file, BytesCodec::new()))),
-                ),
-        )
-        .send()
-        .await?
-        .headers()
-        .get("Location")
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .to_owned();
-
-    spinner.set_message(format!(
-        "Uploading {}: recieved upload location, checking response",
-        filepath
-    ));
-
-    let upload_response = client
-        .post(location)
-        .header("Content-Length", 0)
-        .send()
-        .await?
-        .json::<UploadResponse>()
-        .await
-        .unwrap();
-
-    spinner_task.abort();
-    spinner.set_style(ProgressStyle::with_template("✓ {wide_msg}").unwrap());
+        ),
+        &[],
+        std::path::Path::new(filepath),
+        &spinner,
+    )
+    .await?;
+
     match &upload_response.display_name {
         Some(display_name) => {
-            spinner.finish_with_message(format!("Uploaded file {} as {}", filepath, display_name))
+            spinner.finish(format!("Uploaded file {} as {}", filepath, display_name))
         }
-        None => spinner.finish_with_message(format!("Uploaded file {}", filepath)),
+        None => spinner.finish(format!("Uploaded file {}", filepath)),
     }
 
     Ok(upload_response)
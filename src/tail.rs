@@ -0,0 +1,336 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::{Config, NonEmptyConfig};
+use anyhow::anyhow;
+use canvas_cli::{CanvasId, Course, DateTime};
+use colored::{ColoredString, Colorize};
+use reqwest::Client;
+use serde_derive::Deserialize;
+
+#[derive(clap::Parser, Debug)]
+/// Follow announcements, files, discussions and grades of a course as they happen
+pub struct TailCommand {
+    /// Canvas course ID or SIS course ID (sis_course_id:...)
+    #[clap(long, short)]
+    course: Option<CanvasId>,
+
+    /// Seconds to wait between checking for activity
+    #[clap(long, short, default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+
+    /// Also show activity from this many hours before starting
+    #[clap(long, default_value_t = 0)]
+    hours: u32,
+}
+
+#[derive(Debug)]
+struct Event {
+    /// Identifies the event across polls, so it is only printed once
+    key: String,
+    at: DateTime,
+    kind: ColoredString,
+    text: String,
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}  {:<12}  {}",
+            self.at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+                .dimmed(),
+            self.kind,
+            self.text
+        )
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct AuthorResponse {
+    display_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AnnouncementResponse {
+    id: u32,
+    title: String,
+    posted_at: Option<DateTime>,
+    author: Option<AuthorResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FileResponse {
+    id: u32,
+    display_name: String,
+    created_at: DateTime,
+}
+
+#[derive(Deserialize, Debug)]
+struct DiscussionTopicResponse {
+    id: u32,
+    title: String,
+    posted_at: Option<DateTime>,
+    last_reply_at: Option<DateTime>,
+    discussion_subentry_count: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct SubmissionAssignmentResponse {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SubmissionResponse {
+    assignment_id: u32,
+    assignment: SubmissionAssignmentResponse,
+    grade: Option<String>,
+    graded_at: Option<DateTime>,
+}
+
+impl TailCommand {
+    pub async fn action(&self, cfg: &Config) -> Result<(), anyhow::Error> {
+        let NonEmptyConfig {
+            url: base_url,
+            access_token,
+        } = cfg.ensure_non_empty()?;
+
+        let client = reqwest::Client::builder()
+            .default_headers(
+                std::iter::once((
+                    reqwest::header::AUTHORIZATION,
+                    reqwest::header::HeaderValue::from_str(&format!("Bearer {}", access_token))
+                        .unwrap(),
+                ))
+                .collect(),
+            )
+            .build()
+            .unwrap();
+
+        let mut course_id = self.course.clone();
+        if let Ok(env_canvas_course_id) = std::env::var("CANVAS_COURSE_ID") {
+            course_id = Some(env_canvas_course_id.parse::<CanvasId>().unwrap())
+        }
+
+        let course = Course::fetch_with_colors(course_id.as_ref(), &base_url, &client).await?;
+
+        log::info!("Selected course {}", course.id);
+
+        let since = chrono::Utc::now() - chrono::Duration::hours(self.hours.into());
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut failing: HashSet<&str> = HashSet::new();
+
+        println!("✓ Following {course}, press Ctrl-C to stop");
+
+        loop {
+            let (announcements, files, discussions, grades) = futures::join!(
+                fetch_announcements(&base_url, &client, &course, since),
+                fetch_files(&base_url, &client, &course),
+                fetch_discussions(&base_url, &client, &course),
+                fetch_grades(&base_url, &client, &course),
+            );
+
+            let sources = [
+                ("announcements", announcements),
+                ("files", files),
+                ("discussions", discussions),
+                ("grades", grades),
+            ];
+            let source_count = sources.len();
+            let mut unauthorized = 0;
+
+            // a single failing source, such as a hidden files tab, should not end the feed, but
+            // it should not go unnoticed either
+            let mut events: Vec<Event> = Vec::new();
+            for (source, result) in sources {
+                match result {
+                    Ok(source_events) => {
+                        if failing.remove(source) {
+                            eprintln!("✓ Checking {} works again", source);
+                        }
+                        events.extend(source_events);
+                    }
+                    Err(error) => {
+                        if is_unauthorized(&error) {
+                            unauthorized += 1;
+                        }
+                        if failing.insert(source) {
+                            eprintln!("{} Failed to check {}: {}", "✗".red(), source, error);
+                        } else {
+                            log::warn!("Failed to check {}: {}", source, error);
+                        }
+                    }
+                }
+            }
+
+            if unauthorized == source_count {
+                Err(anyhow!(
+                    "Canvas rejected the access token, run {} auth",
+                    std::env::args()
+                        .next()
+                        .unwrap_or_else(|| "canvas-cli".to_owned())
+                ))?;
+            }
+
+            events.retain(|event| event.at > since && seen.insert(event.key.clone()));
+            events.sort_by_key(|event| event.at);
+            for event in events.iter() {
+                println!("{event}");
+            }
+
+            tokio::time::sleep(std::time::Duration::from_secs(self.interval)).await;
+        }
+    }
+}
+
+fn is_unauthorized(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(|error| error.status())
+        == Some(reqwest::StatusCode::UNAUTHORIZED)
+}
+
+async fn fetch_announcements(
+    url: &str,
+    client: &Client,
+    course: &Course,
+    since: DateTime,
+) -> Result<Vec<Event>, anyhow::Error> {
+    let announcements = client
+        .get(format!("{}/api/v1/announcements", url))
+        .query(&[
+            ("context_codes[]", format!("course_{}", course.id)),
+            ("start_date", since.to_rfc3339()),
+            ("per_page", "100".to_string()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<AnnouncementResponse>>()
+        .await?;
+    log::info!("Made REST request to get announcements");
+
+    Ok(announcements
+        .into_iter()
+        .filter_map(|announcement| {
+            Some(Event {
+                key: format!("announcement:{}", announcement.id),
+                at: announcement.posted_at?,
+                kind: "announcement".cyan(),
+                text: match announcement.author.and_then(|author| author.display_name) {
+                    Some(author) => format!("{} ({})", announcement.title, author),
+                    None => announcement.title,
+                },
+            })
+        })
+        .collect())
+}
+
+async fn fetch_files(
+    url: &str,
+    client: &Client,
+    course: &Course,
+) -> Result<Vec<Event>, anyhow::Error> {
+    let files = client
+        .get(format!(
+            "{}/api/v1/courses/{}/files?sort=created_at&order=desc&per_page=100",
+            url, course.id
+        ))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<FileResponse>>()
+        .await?;
+    log::info!("Made REST request to get files");
+
+    Ok(files
+        .into_iter()
+        .map(|file| Event {
+            key: format!("file:{}", file.id),
+            at: file.created_at,
+            kind: "file".blue(),
+            text: file.display_name,
+        })
+        .collect())
+}
+
+async fn fetch_discussions(
+    url: &str,
+    client: &Client,
+    course: &Course,
+) -> Result<Vec<Event>, anyhow::Error> {
+    let topics = client
+        .get(format!(
+            "{}/api/v1/courses/{}/discussion_topics?order_by=recent_activity&per_page=100",
+            url, course.id
+        ))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<DiscussionTopicResponse>>()
+        .await?;
+    log::info!("Made REST request to get discussion topics");
+
+    let mut events = Vec::new();
+    for topic in topics.into_iter() {
+        if let Some(posted_at) = topic.posted_at {
+            events.push(Event {
+                key: format!("discussion:{}", topic.id),
+                at: posted_at,
+                kind: "discussion".magenta(),
+                text: format!("New topic {}", topic.title),
+            });
+        }
+
+        if let Some(last_reply_at) = topic.last_reply_at {
+            events.push(Event {
+                key: format!("reply:{}:{}", topic.id, last_reply_at),
+                at: last_reply_at,
+                kind: "reply".magenta(),
+                text: format!(
+                    "{} ({} replies)",
+                    topic.title, topic.discussion_subentry_count
+                ),
+            });
+        }
+    }
+
+    Ok(events)
+}
+
+async fn fetch_grades(
+    url: &str,
+    client: &Client,
+    course: &Course,
+) -> Result<Vec<Event>, anyhow::Error> {
+    let submissions = client
+        .get(format!(
+            "{}/api/v1/courses/{}/students/submissions?student_ids[]=self&include[]=assignment&per_page=1000",
+            url, course.id
+        ))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<SubmissionResponse>>()
+        .await?;
+    log::info!("Made REST request to get submissions");
+
+    Ok(submissions
+        .into_iter()
+        .filter_map(|submission| {
+            let graded_at = submission.graded_at?;
+            Some(Event {
+                key: format!("grade:{}:{}", submission.assignment_id, graded_at),
+                at: graded_at,
+                kind: "grade".green(),
+                text: format!(
+                    "{} graded {}",
+                    submission.assignment.name,
+                    submission.grade.as_deref().unwrap_or("without a grade")
+                ),
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,98 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::anyhow;
+use reqwest::{
+    multipart::{Form, Part},
+    Body, Client,
+};
+use serde_derive::Deserialize;
+use tokio_util::codec::{BytesCodec, FramedRead};
+
+use crate::progress::Spinner;
+
+#[derive(Deserialize, Debug)]
+struct UploadBucket {
+    upload_url: String,
+    upload_params: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UploadResponse {
+    pub id: u32,
+    pub display_name: Option<String>,
+}
+
+/// Upload a file through the Canvas file upload process
+///
+/// `bucket_url` is the endpoint handing out the upload bucket, which decides where the file ends
+/// up, and `params` are sent to it along with the name and size of the file.
+pub async fn upload_file(
+    client: &Client,
+    bucket_url: &str,
+    params: &[(&str, &str)],
+    path: &Path,
+    spinner: &Spinner,
+) -> Result<UploadResponse, anyhow::Error> {
+    let filepath = path.display();
+    let metadata = tokio::fs::metadata(path).await?;
+    let file = tokio::fs::File::open(path).await?;
+    let basename = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("Invalid file name: {}", filepath))?;
+
+    let size = metadata.len().to_string();
+    let mut form = vec![("name", basename), ("size", size.as_str())];
+    form.extend_from_slice(params);
+
+    let upload_bucket = client
+        .post(bucket_url)
+        .form(&form)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<UploadBucket>()
+        .await?;
+
+    spinner.set_message(format!(
+        "Uploading {}: recieved upload bucket, sending file payload",
+        filepath
+    ));
+
+    let location = client
+        .post(upload_bucket.upload_url)
+        .multipart(
+            upload_bucket
+                .upload_params
+                .into_iter()
+                .fold(Form::new(), |form, (k, v)| form.text(k, v))
+                .part(
+                    "file",
+                    Part::stream(Body::wrap_stream(FramedRead::new(file, BytesCodec::new()))),
+                ),
+        )
+        .send()
+        .await?
+        .error_for_status()?
+        .headers()
+        .get("Location")
+        .ok_or_else(|| anyhow!("Upload of {} did not return a location", filepath))?
+        .to_str()?
+        .to_owned();
+
+    spinner.set_message(format!(
+        "Uploading {}: recieved upload location, checking response",
+        filepath
+    ));
+
+    let upload_response = client
+        .post(location)
+        .header("Content-Length", 0)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<UploadResponse>()
+        .await?;
+
+    Ok(upload_response)
+}